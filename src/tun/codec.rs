@@ -0,0 +1,186 @@
+use std::io;
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::codec::{Decoder, Encoder};
+
+/// EtherType carried in the packet information header for IPv4 packets.
+const ETH_P_IP: u16 = 0x0800;
+/// EtherType carried in the packet information header for IPv6 packets.
+const ETH_P_IPV6: u16 = 0x86DD;
+
+/// A `Decoder`/`Encoder` of whole IP packets, for use with `Framed` to turn
+/// a [`Tun`](crate::tun::Tun) into a `Stream`/`Sink` of `Bytes` instead of
+/// using the raw `Read`/`Write`/`AsyncRead`/`AsyncWrite` impls directly.
+///
+/// Each `decode`/`encode` call corresponds to exactly one packet, since
+/// each read from (and write to) a tun device is itself one whole datagram.
+///
+/// When the underlying device was *not* created with `IFF_NO_PI` (i.e. it
+/// was constructed with `Tun::new(true)`), the kernel prefixes every
+/// datagram with a 4-byte packet information header: 2 bytes of flags
+/// followed by a big-endian EtherType. `TunPacketCodec` strips that header
+/// on decode, and reconstructs it on encode by sniffing the IP version
+/// nibble of the outgoing packet. `packet_information` must match the flag
+/// the `Tun` was constructed with, or decoding/encoding will be wrong.
+pub struct TunPacketCodec {
+    packet_information: bool,
+}
+
+impl TunPacketCodec {
+    /// Create a codec for a `Tun` that was constructed with the given
+    /// `packet_information` flag.
+    pub fn new(packet_information: bool) -> Self {
+        TunPacketCodec { packet_information }
+    }
+}
+
+impl Decoder for TunPacketCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if buf.is_empty() {
+            return Ok(None);
+        }
+
+        let len = buf.len();
+        let mut pkt = buf.split_to(len);
+
+        if self.packet_information {
+            if pkt.len() < 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "packet too short to contain a packet information header",
+                ));
+            }
+            pkt.advance(4);
+        }
+
+        Ok(Some(pkt.freeze()))
+    }
+}
+
+impl Encoder for TunPacketCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if self.packet_information {
+            let ethertype = match item.get(0).map(|b| b >> 4) {
+                Some(4) => ETH_P_IP,
+                Some(6) => ETH_P_IPV6,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "unable to determine IP version of outgoing packet",
+                    ))
+                }
+            };
+
+            dst.reserve(4 + item.len());
+            dst.extend_from_slice(&[0u8, 0u8]);
+            dst.extend_from_slice(&ethertype.to_be_bytes());
+        } else {
+            dst.reserve(item.len());
+        }
+
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_without_packet_information_returns_whole_buffer() {
+        let mut codec = TunPacketCodec::new(false);
+        let mut buf = BytesMut::from(&b"\x45\x00hello"[..]);
+
+        let pkt = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(&pkt[..], &b"\x45\x00hello"[..]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn decode_empty_buffer_returns_none() {
+        let mut codec = TunPacketCodec::new(false);
+        let mut buf = BytesMut::new();
+
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn decode_with_packet_information_strips_header() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut buf = BytesMut::from(&[0u8, 0u8, 0x08, 0x00, 0x45, 0x00][..]);
+
+        let pkt = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(&pkt[..], &[0x45, 0x00]);
+    }
+
+    #[test]
+    fn decode_with_packet_information_errors_on_short_packet() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut buf = BytesMut::from(&[0u8, 0u8, 0x08][..]);
+
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encode_without_packet_information_copies_packet_unchanged() {
+        let mut codec = TunPacketCodec::new(false);
+        let mut dst = BytesMut::new();
+
+        codec
+            .encode(Bytes::from_static(&[0x45, 0x00]), &mut dst)
+            .unwrap();
+        assert_eq!(&dst[..], &[0x45, 0x00]);
+    }
+
+    #[test]
+    fn encode_with_packet_information_prepends_ipv4_header() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut dst = BytesMut::new();
+
+        codec
+            .encode(Bytes::from_static(&[0x45, 0x00]), &mut dst)
+            .unwrap();
+        assert_eq!(&dst[..], &[0x00, 0x00, 0x08, 0x00, 0x45, 0x00]);
+    }
+
+    #[test]
+    fn encode_with_packet_information_prepends_ipv6_header() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut dst = BytesMut::new();
+
+        codec
+            .encode(Bytes::from_static(&[0x60, 0x00]), &mut dst)
+            .unwrap();
+        assert_eq!(&dst[..], &[0x00, 0x00, 0x86, 0xDD, 0x60, 0x00]);
+    }
+
+    #[test]
+    fn encode_with_packet_information_errors_on_unknown_ip_version() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut dst = BytesMut::new();
+
+        let err = codec
+            .encode(Bytes::from_static(&[0x00, 0x00]), &mut dst)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn encode_then_decode_round_trips_with_packet_information() {
+        let mut codec = TunPacketCodec::new(true);
+        let mut dst = BytesMut::new();
+        let original = Bytes::from_static(&[0x45, 0x00, 0x01, 0x02]);
+
+        codec.encode(original.clone(), &mut dst).unwrap();
+        let pkt = codec.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(pkt, original);
+    }
+}
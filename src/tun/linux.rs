@@ -0,0 +1,309 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::Ipv4Addr;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use mio::event::Evented;
+use mio::unix::EventedFd;
+use mio::{Poll as MioPoll, PollOpt, Ready, Token};
+use nix::fcntl::{open, OFlag};
+use nix::libc::{self, c_char, c_short, sockaddr, sockaddr_in, AF_INET};
+use nix::sys::socket::{socket, AddressFamily, SockFlag, SockType};
+use nix::sys::stat::Mode;
+use nix::unistd::close;
+
+use super::{ifreq_addr, ifreq_flags, ifreq_mtu, IFNAMSIZ};
+use crate::try_nix;
+
+const TUN_DEV_PATH: &str = "/dev/net/tun";
+
+const IFF_TUN: c_short = 0x0001;
+const IFF_NO_PI: c_short = 0x1000;
+const IFF_MULTI_QUEUE: c_short = 0x0100;
+const IFF_ATTACH_QUEUE: c_short = 0x0200;
+const IFF_DETACH_QUEUE: c_short = 0x0400;
+
+const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+const TUNSETQUEUE: libc::c_ulong = 0x4004_54d9;
+
+const SIOCSIFADDR: libc::c_ulong = 0x8916;
+const SIOCGIFADDR: libc::c_ulong = 0x8915;
+const SIOCSIFNETMASK: libc::c_ulong = 0x891c;
+const SIOCGIFNETMASK: libc::c_ulong = 0x891b;
+const SIOCGIFMTU: libc::c_ulong = 0x8921;
+const SIOCSIFMTU: libc::c_ulong = 0x8922;
+
+fn name_to_ifrn(name: &str) -> [c_char; IFNAMSIZ] {
+    let mut ifrn = [0 as c_char; IFNAMSIZ];
+    for (dst, src) in ifrn.iter_mut().zip(name.as_bytes()) {
+        *dst = *src as c_char;
+    }
+    ifrn
+}
+
+fn ifrn_to_name(ifrn: &[c_char; IFNAMSIZ]) -> String {
+    let bytes: Vec<u8> = ifrn
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn ipv4_sockaddr(addr: Ipv4Addr) -> sockaddr {
+    let mut sin: sockaddr_in = unsafe { mem::zeroed() };
+    sin.sin_family = AF_INET as _;
+    sin.sin_addr.s_addr = u32::from_ne_bytes(addr.octets());
+    unsafe { mem::transmute(sin) }
+}
+
+fn sockaddr_ipv4(addr: sockaddr) -> Ipv4Addr {
+    let sin: sockaddr_in = unsafe { mem::transmute(addr) };
+    Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes())
+}
+
+/// Open an ioctl-only `AF_INET`/`SOCK_DGRAM` socket used to carry the
+/// `SIOC*IF*` address/netmask/MTU ioctls, which (unlike `TUNSET*`) aren't
+/// issued against the tun device's own file descriptor.
+fn ioctl_socket() -> io::Result<RawFd> {
+    try_nix!(socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::empty(),
+        None,
+    ))
+}
+
+/// The Linux backend of [`crate::tun::Tun`]: a `/dev/net/tun` file
+/// descriptor created with `TUNSETIFF`.
+pub struct Tun {
+    fd: RawFd,
+    name: String,
+}
+
+impl Tun {
+    /// Open `/dev/net/tun` and create an `IFF_TUN` interface, honoring
+    /// `packet_information` by setting (or clearing) `IFF_NO_PI`.
+    pub fn new(packet_information: bool) -> io::Result<Tun> {
+        Tun::new_named("", packet_information, false)
+    }
+
+    /// Open `count` queues against one `IFF_MULTI_QUEUE` netdev: the first
+    /// open lets the kernel pick the interface name, every subsequent open
+    /// reuses it so all queues land on the same device.
+    pub fn new_multi_queue(count: usize, packet_information: bool) -> io::Result<Vec<Tun>> {
+        let mut tuns = Vec::with_capacity(count);
+        let mut name = String::new();
+        for _ in 0..count {
+            let tun = Tun::new_named(&name, packet_information, true)?;
+            name = tun.name.clone();
+            tuns.push(tun);
+        }
+        Ok(tuns)
+    }
+
+    /// Shared by [`Tun::new`] and [`Tun::new_multi_queue`]: `name` may be
+    /// empty to let the kernel pick one, which is read back from the
+    /// resulting `ifreq`; `multi_queue` additionally sets `IFF_MULTI_QUEUE`.
+    fn new_named(name: &str, packet_information: bool, multi_queue: bool) -> io::Result<Tun> {
+        // Non-blocking so the EAGAIN-to-WouldBlock branches in
+        // read_vectored/write_vectored are reachable instead of blocking
+        // the reactor thread on a stale readiness notification.
+        let fd = try_nix!(open(
+            TUN_DEV_PATH,
+            OFlag::O_RDWR | OFlag::O_NONBLOCK,
+            Mode::empty(),
+        ));
+
+        let mut flags = IFF_TUN;
+        if !packet_information {
+            flags |= IFF_NO_PI;
+        }
+        if multi_queue {
+            flags |= IFF_MULTI_QUEUE;
+        }
+
+        let mut ifr = ifreq_flags {
+            ifra_name: name_to_ifrn(name),
+            ifra_flags: flags,
+        };
+
+        if unsafe { libc::ioctl(fd, TUNSETIFF, &mut ifr) } < 0 {
+            let err = io::Error::last_os_error();
+            let _ = close(fd);
+            return Err(err);
+        }
+
+        Ok(Tun {
+            fd,
+            name: ifrn_to_name(&ifr.ifra_name),
+        })
+    }
+
+    /// Attach or detach this queue from its multi-queue netdev via
+    /// `TUNSETQUEUE`, without closing the file descriptor.
+    pub fn set_queue_attached(&self, attached: bool) -> io::Result<()> {
+        let mut ifr = ifreq_flags {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_flags: if attached {
+                IFF_ATTACH_QUEUE
+            } else {
+                IFF_DETACH_QUEUE
+            },
+        };
+
+        if unsafe { libc::ioctl(self.fd, TUNSETQUEUE, &mut ifr) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Get interface name from the underlying Tun.
+    pub fn ifname(&self) -> io::Result<String> {
+        Ok(self.name.clone())
+    }
+
+    /// Set address of the Tun interface
+    pub fn set_addr(&self, addr: Ipv4Addr) -> io::Result<()> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_addr {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_addr: ipv4_sockaddr(addr),
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCSIFADDR, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Get address of the Tun interface
+    pub fn addr(&self) -> io::Result<Ipv4Addr> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_addr {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_addr: unsafe { mem::zeroed() },
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCGIFADDR, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sockaddr_ipv4(ifr.ifra_addr))
+    }
+
+    /// Set netmask of the Tun interface
+    pub fn set_netmask(&self, netmask: Ipv4Addr) -> io::Result<()> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_addr {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_addr: ipv4_sockaddr(netmask),
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCSIFNETMASK, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Get netmask of the Tun interface
+    pub fn netmask(&self) -> io::Result<Ipv4Addr> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_addr {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_addr: unsafe { mem::zeroed() },
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCGIFNETMASK, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sockaddr_ipv4(ifr.ifra_addr))
+    }
+
+    /// Get the MTU of the Tun interface via `SIOCGIFMTU`.
+    pub fn mtu(&self) -> io::Result<u32> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_mtu {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_mtu: 0,
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCGIFMTU, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ifr.ifra_mtu as u32)
+    }
+
+    /// Set the MTU of the Tun interface via `SIOCSIFMTU`.
+    pub fn set_mtu(&self, mtu: u32) -> io::Result<()> {
+        let sock = ioctl_socket()?;
+        let mut ifr = ifreq_mtu {
+            ifra_name: name_to_ifrn(&self.name),
+            ifra_mtu: mtu as i32,
+        };
+        let result = unsafe { libc::ioctl(sock, SIOCSIFMTU, &mut ifr) };
+        let _ = close(sock);
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl AsRawFd for Tun {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Read for Tun {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try_nix!(::nix::unistd::read(self.fd, buf));
+        Ok(n)
+    }
+}
+
+impl Write for Tun {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try_nix!(::nix::unistd::write(self.fd, buf));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Evented for Tun {
+    fn register(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.fd).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.fd).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &MioPoll) -> io::Result<()> {
+        EventedFd(&self.fd).deregister(poll)
+    }
+}
+
+impl Drop for Tun {
+    fn drop(&mut self) {
+        let _ = close(self.fd);
+    }
+}
+
+impl fmt::Debug for Tun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tun")
+            .field("fd", &self.fd)
+            .field("name", &self.name)
+            .finish()
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 use std::io::{self, Read, Write};
 
-use bytes::{Buf, BufMut, Bytes};
+use bytes::{Buf, BufMut};
 use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
 use mio::Ready;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -10,6 +10,8 @@ use tokio::reactor::PollEvented2;
 #[cfg(not(windows))]
 use nix::libc::{c_char, c_short, sockaddr};
 #[cfg(not(windows))]
+use nix::sys::uio::{readv, writev, IoVec};
+#[cfg(not(windows))]
 use std::net::Ipv4Addr;
 #[cfg(not(windows))]
 use std::os::unix::io::{AsRawFd, RawFd};
@@ -21,6 +23,12 @@ use winapi::shared::guiddef::GUID;
 
 use crate::try_nb;
 
+#[cfg(feature = "codec")]
+pub mod codec;
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+pub mod uring;
+
 #[cfg(not(windows))]
 fn from_nix_error(err: ::nix::Error) -> io::Error {
     match err {
@@ -62,6 +70,13 @@ pub struct ifreq_flags {
     pub ifra_flags: c_short,
 }
 
+#[cfg(not(windows))]
+#[repr(C)]
+pub struct ifreq_mtu {
+    pub ifra_name: [c_char; IFNAMSIZ],
+    pub ifra_mtu: i32,
+}
+
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 #[path = "macos.rs"]
 pub mod platform;
@@ -74,30 +89,62 @@ pub mod platform;
 #[path = "windows.rs"]
 pub mod platform;
 
+/// MTU assumed for platforms (currently just Windows/wintun) that don't yet
+/// expose an ioctl to query it.
+#[cfg(windows)]
+const DEFAULT_MTU: u32 = 1500;
+
 pub struct Tun {
     io: PollEvented2<platform::Tun>,
+    packet_information: bool,
+    mtu: u32,
 }
 
 impl Tun {
     /// New Tun to the existing event pool.
+    ///
+    /// `packet_information` selects whether the device is created with
+    /// `IFF_NO_PI` (`false`, the previous default) or left with packet
+    /// information enabled (`true`), in which case every datagram read from
+    /// or written to the device is prefixed with the 4-byte header the
+    /// kernel uses to carry the packet's EtherType. [`codec::TunPacketCodec`]
+    /// must be constructed with the same value to frame packets correctly.
     #[cfg(not(windows))]
-    pub fn new() -> io::Result<Tun> {
-        Tun::from_tun(platform::Tun::new()?)
+    pub fn new(packet_information: bool) -> io::Result<Tun> {
+        Tun::from_tun(platform::Tun::new(packet_information)?, packet_information)
     }
 
     /// New Tun to the existing event pool.
     #[cfg(windows)]
     pub fn new(ifname: String, description: String, requested_guid: &GUID) -> io::Result<Tun> {
-        Tun::from_tun(platform::Tun::new(ifname, description, requested_guid)?)
+        Tun::from_tun(
+            platform::Tun::new(ifname, description, requested_guid)?,
+            false,
+        )
     }
 
     /// New Tun to the existing event pool from the existig underlying Tun implementation.
-    pub fn from_tun(tun: platform::Tun) -> io::Result<Tun> {
+    pub fn from_tun(tun: platform::Tun, packet_information: bool) -> io::Result<Tun> {
+        #[cfg(not(windows))]
+        let mtu = tun.mtu()?;
+        #[cfg(windows)]
+        let mtu = DEFAULT_MTU;
+
         Ok(Tun {
             io: PollEvented2::new(tun),
+            packet_information,
+            mtu,
         })
     }
 
+    /// Whether this `Tun` was created with packet information enabled, i.e.
+    /// every datagram is prefixed with the kernel's 4-byte packet info
+    /// header. See [`Tun::new`].
+    #[cfg(not(windows))]
+    pub fn packet_information(&self) -> bool {
+        self.packet_information
+    }
+
     /// Get interface name from the underlying Tun.
     pub fn ifname(&self) -> io::Result<String> {
         self.io.get_ref().ifname()
@@ -139,6 +186,122 @@ impl Tun {
         self.io.get_ref().netmask()
     }
 
+    /// Get the cached MTU of the Tun interface, as of the last `from_tun`
+    /// or `set_mtu` call.
+    #[cfg(not(windows))]
+    pub fn mtu(&self) -> u32 {
+        self.mtu
+    }
+
+    /// Set the MTU of the Tun interface, and update the cached value used
+    /// to size the buffers the async read paths allocate.
+    #[cfg(not(windows))]
+    pub fn set_mtu(&mut self, mtu: u32) -> io::Result<()> {
+        self.io.get_mut().set_mtu(mtu)?;
+        self.mtu = mtu;
+        Ok(())
+    }
+
+    /// Size, in bytes, of a buffer large enough to hold one packet read from
+    /// this Tun, including the packet information header if enabled.
+    fn read_buf_size(&self) -> usize {
+        self.mtu as usize + if self.packet_information { 4 } else { 0 }
+    }
+
+    /// Read one packet into `bufs` using `readv`, scattering it across the
+    /// given buffers without an intermediate stack-buffer copy; `read_buf`
+    /// and the `Stream` impl read through it. Returns `WouldBlock` if the Tun isn't
+    /// currently readable; callers driving this directly (outside of the
+    /// `Stream`/`AsyncRead` impls) are responsible for waiting on
+    /// `poll_read_ready_readable` first.
+    #[cfg(not(windows))]
+    pub fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        if let Async::NotReady = self.io.poll_read_ready(Ready::readable())? {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let iov: Vec<IoVec<&mut [u8]>> = bufs.iter_mut().map(|b| IoVec::from_mut_slice(b)).collect();
+        let read_result = readv(self.as_raw_fd(), &iov);
+        match read_result {
+            Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => {
+                self.io.clear_read_ready(Ready::readable())?;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Err(e) => Err(from_nix_error(e)),
+            Ok(bytes_read) => Ok(bytes_read),
+        }
+    }
+
+    /// Scalar fallback for platforms (e.g. the Windows wintun backend) that
+    /// can't express vectored reads: reads into the first non-empty buffer.
+    #[cfg(windows)]
+    pub fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
+    }
+
+    /// Write one packet gathered from `bufs` using `writev`, without the
+    /// intermediate copy a single contiguous write would need.
+    #[cfg(not(windows))]
+    pub fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if let Async::NotReady = self.io.poll_write_ready()? {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let iov: Vec<IoVec<&[u8]>> = bufs.iter().map(|b| IoVec::from_slice(b)).collect();
+        let write_result = writev(self.as_raw_fd(), &iov);
+        match write_result {
+            Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => {
+                self.io.clear_write_ready()?;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Err(e) => Err(from_nix_error(e)),
+            Ok(bytes_written) => Ok(bytes_written),
+        }
+    }
+
+    /// Scalar fallback for platforms (e.g. the Windows wintun backend) that
+    /// can't express vectored writes: writes the first non-empty buffer.
+    #[cfg(windows)]
+    pub fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        match bufs.iter().find(|b| !b.is_empty()) {
+            Some(buf) => self.write(buf),
+            None => Ok(0),
+        }
+    }
+
+    /// Create `count` queues (`IFF_MULTI_QUEUE`) on one Tun netdev, each its
+    /// own file descriptor sharing the interface name and `packet_information`
+    /// flag. A queue can be detached without closing it via `set_queue_attached`.
+    #[cfg(target_os = "linux")]
+    pub fn new_multi_queue(count: usize, packet_information: bool) -> io::Result<Vec<Tun>> {
+        platform::Tun::new_multi_queue(count, packet_information)?
+            .into_iter()
+            .map(|tun| Tun::from_tun(tun, packet_information))
+            .collect()
+    }
+
+    /// Attach or detach this queue from its multi-queue Tun netdev via
+    /// `TUNSETQUEUE`, without tearing down the file descriptor. A detached
+    /// queue stops receiving packets destined for the interface until
+    /// reattached.
+    #[cfg(target_os = "linux")]
+    pub fn set_queue_attached(&self, attached: bool) -> io::Result<()> {
+        self.io.get_ref().set_queue_attached(attached)
+    }
+
+    /// Tear down the readiness-based `PollEvented2` wrapper and hand the
+    /// underlying `platform::Tun` to a completion-based `uring::UringTun`
+    /// that shares the same file descriptor, reads and writes via io_uring
+    /// SQEs/CQEs instead of polling for readiness and retrying on
+    /// `WouldBlock`.
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    pub fn into_uring(self) -> io::Result<uring::UringTun> {
+        uring::UringTun::new(self.io.into_inner()?)
+    }
+
     /// Poll Tun for read
     pub fn poll_read_ready_readable(&self) -> io::Result<Async<Ready>> {
         self.io.poll_read_ready(Ready::readable())
@@ -195,23 +358,22 @@ impl AsyncRead for Tun {
     }
 
     fn read_buf<B: BufMut>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
-        if let Async::NotReady = self.io.poll_read_ready(Ready::readable())? {
-            return Ok(Async::NotReady);
+        if buf.remaining_mut() == 0 {
+            return Ok(Async::Ready(0));
         }
 
-        let mut stack_buf = [0u8; 1600]; // TODO: Use MTU
-        let read_result = self.io.read(&mut stack_buf);
-        match read_result {
+        let dst = unsafe { buf.bytes_mut() };
+        let mut iov = [io::IoSliceMut::new(dst)];
+        match self.read_vectored(&mut iov) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
-                    self.io.clear_read_ready(Ready::readable())?;
                     Ok(Async::NotReady)
                 } else {
                     Err(e)
                 }
             }
             Ok(bytes_read) => {
-                buf.put_slice(&stack_buf[0..bytes_read]);
+                unsafe { buf.advance_mut(bytes_read) };
                 Ok(Async::Ready(bytes_read))
             }
         }
@@ -224,16 +386,15 @@ impl AsyncWrite for Tun {
     }
 
     fn write_buf<B: Buf>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
-        if let Async::NotReady = self.io.poll_write_ready()? {
-            return Ok(Async::NotReady);
+        if !buf.has_remaining() {
+            return Ok(Async::Ready(0));
         }
 
-        let bytes: Bytes = buf.collect();
-        let write_result = self.io.write(&bytes[..]);
-        match write_result {
+        let src = buf.bytes();
+        let iov = [io::IoSlice::new(src)];
+        match self.write_vectored(&iov) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
-                    self.io.clear_write_ready()?;
                     Ok(Async::NotReady)
                 } else {
                     Err(e)
@@ -242,7 +403,7 @@ impl AsyncWrite for Tun {
             Ok(bytes_written) => {
                 buf.advance(bytes_written);
 
-                if bytes_written < bytes.len() {
+                if bytes_written < src.len() {
                     Err(io::Error::new(
                         io::ErrorKind::WriteZero,
                         "failed to write packet to tun",
@@ -260,16 +421,11 @@ impl Stream for Tun {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        if let Async::NotReady = self.io.poll_read_ready(Ready::readable())? {
-            return Ok(Async::NotReady);
-        }
-
-        let mut buf = vec![0u8; 1600]; // TODO: Use MTU
-        let read_result = self.io.read(&mut buf);
-        match read_result {
+        let mut buf = vec![0u8; self.read_buf_size()];
+        let mut iov = [io::IoSliceMut::new(&mut buf)];
+        match self.read_vectored(&mut iov) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
-                    self.io.clear_read_ready(Ready::readable())?;
                     Ok(Async::NotReady)
                 } else {
                     Err(e)
@@ -288,15 +444,10 @@ impl Sink for Tun {
     type SinkError = io::Error;
 
     fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
-        if let Async::NotReady = self.io.poll_write_ready()? {
-            return Ok(AsyncSink::NotReady(item));
-        }
-
-        let write_result = self.io.write(&item[..]);
-        match write_result {
+        let iov = [io::IoSlice::new(&item)];
+        match self.write_vectored(&iov) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
-                    self.io.clear_write_ready()?;
                     Ok(AsyncSink::NotReady(item))
                 } else {
                     Err(e)
@@ -0,0 +1,254 @@
+//! Completion-based (io_uring) backend, feature-gated as an alternative to
+//! the readiness-based `PollEvented2<platform::Tun>` path the rest of this
+//! crate uses. `Tun::into_uring` opts a single `Tun` into it, sharing its
+//! `platform::Tun`'s raw fd.
+
+use std::io::{self, Read};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use futures::{Async, Future, Poll};
+use io_uring::{opcode, types, IoUring};
+use mio::event::Evented;
+use mio::unix::EventedFd;
+use mio::{Poll as MioPoll, PollOpt, Ready, Token};
+use nix::sys::eventfd::{eventfd, EfdFlags};
+use nix::unistd::close;
+use tokio::reactor::PollEvented2;
+
+use super::platform;
+use crate::try_nix;
+
+/// An eventfd registered with the ring via `IORING_REGISTER_EVENTFD`: the
+/// kernel writes to it every time it posts a CQE, so wrapping it in a
+/// `PollEvented2` gives `ReadOwned`/`WriteOwned` a real epoll-backed wakeup
+/// instead of polling the completion queue in a spin loop.
+struct EventFd(RawFd);
+
+impl AsRawFd for EventFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Read for EventFd {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try_nix!(::nix::unistd::read(self.0, buf));
+        Ok(n)
+    }
+}
+
+impl Evented for EventFd {
+    fn register(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &MioPoll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &MioPoll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) {
+        let _ = close(self.0);
+    }
+}
+
+/// A `platform::Tun` driven through an io_uring submission/completion
+/// queue instead of the default readiness-based `PollEvented2` backend.
+///
+/// Only one `ReadOwned`/`WriteOwned` can be outstanding at a time: both
+/// borrow `&mut UringTun` for their whole lifetime, so a second op can't be
+/// constructed until the first resolves or is dropped. Driving several ops
+/// concurrently means giving each its own `UringTun`/ring.
+pub struct UringTun {
+    tun: platform::Tun,
+    ring: IoUring,
+    notify: PollEvented2<EventFd>,
+}
+
+impl UringTun {
+    /// Wrap a raw `platform::Tun` with its own io_uring instance, and
+    /// register an eventfd with it so completions can wake a parked task.
+    pub fn new(tun: platform::Tun) -> io::Result<UringTun> {
+        let ring = IoUring::new(256)?;
+
+        let efd = try_nix!(eventfd(0, EfdFlags::EFD_NONBLOCK | EfdFlags::EFD_CLOEXEC));
+        if let Err(err) = ring.submitter().register_eventfd(efd) {
+            let _ = close(efd);
+            return Err(err);
+        }
+
+        Ok(UringTun {
+            tun,
+            ring,
+            notify: PollEvented2::new(EventFd(efd)),
+        })
+    }
+
+    /// Read one packet into `buf`, taking ownership of it for the duration
+    /// of the operation and handing it back on completion alongside the
+    /// number of bytes read.
+    pub fn read_owned(&mut self, buf: Vec<u8>) -> ReadOwned {
+        ReadOwned {
+            tun: self,
+            buf: Some(buf),
+            submitted: false,
+        }
+    }
+
+    /// Write `buf` as one packet, taking ownership of it for the duration
+    /// of the operation and handing it back on completion alongside the
+    /// number of bytes written.
+    pub fn write_owned(&mut self, buf: Vec<u8>) -> WriteOwned {
+        WriteOwned {
+            tun: self,
+            buf: Some(buf),
+            submitted: false,
+        }
+    }
+
+    fn fd(&self) -> RawFd {
+        self.tun.as_raw_fd()
+    }
+
+    /// Called when the completion queue was found empty: park on the
+    /// eventfd's readiness via the reactor, draining it first if it's
+    /// already readable from a completion that raced us.
+    fn park_for_completion(&mut self) -> Poll<(), io::Error> {
+        if let Async::NotReady = self.notify.poll_read_ready(Ready::readable())? {
+            return Ok(Async::NotReady);
+        }
+
+        self.notify.clear_read_ready(Ready::readable())?;
+        let mut counter = [0u8; 8];
+        match self.notify.get_mut().read(&mut counter) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+/// Future returned by [`UringTun::read_owned`]; resolves to the number of
+/// bytes read and the buffer that was read into.
+pub struct ReadOwned<'a> {
+    tun: &'a mut UringTun,
+    buf: Option<Vec<u8>>,
+    submitted: bool,
+}
+
+impl<'a> Future for ReadOwned<'a> {
+    type Item = (usize, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut buf = self.buf.take().expect("ReadOwned polled after completion");
+
+        if !self.submitted {
+            let fd = types::Fd(self.tun.fd());
+            let entry = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as _).build();
+            unsafe {
+                self.tun.ring.submission().push(&entry).map_err(|_| {
+                    io::Error::new(io::ErrorKind::Other, "io_uring submission queue full")
+                })?;
+            }
+            self.tun.ring.submit()?;
+            self.submitted = true;
+        }
+
+        match self.tun.ring.completion().next() {
+            Some(cqe) => {
+                let result = cqe.result();
+                if result < 0 {
+                    Err(io::Error::from_raw_os_error(-result))
+                } else {
+                    Ok(Async::Ready((result as usize, buf)))
+                }
+            }
+            None => {
+                self.buf = Some(buf);
+                if let Async::NotReady = self.tun.park_for_completion()? {
+                    return Ok(Async::NotReady);
+                }
+                self.poll()
+            }
+        }
+    }
+}
+
+impl<'a> Drop for ReadOwned<'a> {
+    fn drop(&mut self) {
+        // The kernel may still hold a pointer into `buf` from an SQE we
+        // already submitted; block for its CQE before the buffer is freed
+        // rather than leaving a dangling read in flight.
+        if self.submitted && self.buf.is_some() {
+            while self.tun.ring.completion().next().is_none() {
+                let _ = self.tun.ring.submit_and_wait(1);
+            }
+        }
+    }
+}
+
+/// Future returned by [`UringTun::write_owned`]; resolves to the number of
+/// bytes written and the buffer that was written from.
+pub struct WriteOwned<'a> {
+    tun: &'a mut UringTun,
+    buf: Option<Vec<u8>>,
+    submitted: bool,
+}
+
+impl<'a> Future for WriteOwned<'a> {
+    type Item = (usize, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let buf = self.buf.take().expect("WriteOwned polled after completion");
+
+        if !self.submitted {
+            let fd = types::Fd(self.tun.fd());
+            let entry = opcode::Write::new(fd, buf.as_ptr(), buf.len() as _).build();
+            unsafe {
+                self.tun.ring.submission().push(&entry).map_err(|_| {
+                    io::Error::new(io::ErrorKind::Other, "io_uring submission queue full")
+                })?;
+            }
+            self.tun.ring.submit()?;
+            self.submitted = true;
+        }
+
+        match self.tun.ring.completion().next() {
+            Some(cqe) => {
+                let result = cqe.result();
+                if result < 0 {
+                    Err(io::Error::from_raw_os_error(-result))
+                } else {
+                    Ok(Async::Ready((result as usize, buf)))
+                }
+            }
+            None => {
+                self.buf = Some(buf);
+                if let Async::NotReady = self.tun.park_for_completion()? {
+                    return Ok(Async::NotReady);
+                }
+                self.poll()
+            }
+        }
+    }
+}
+
+impl<'a> Drop for WriteOwned<'a> {
+    fn drop(&mut self) {
+        // Same reasoning as ReadOwned::drop: the kernel may still be
+        // reading from `buf` via an already-submitted SQE.
+        if self.submitted && self.buf.is_some() {
+            while self.tun.ring.completion().next().is_none() {
+                let _ = self.tun.ring.submit_and_wait(1);
+            }
+        }
+    }
+}